@@ -1,6 +1,8 @@
 use std::{
     alloc, fmt,
+    mem::{align_of, size_of},
     ptr::{self, NonNull},
+    sync::Arc,
 };
 
 use metrics::Label;
@@ -9,11 +11,44 @@ use smallbox::smallbox;
 
 use crate::dumping;
 
-pub use self::{any::*, protocol::*, repr::*};
+pub use self::{any::*, codec::*, protocol::*, repr::*, serialized::*};
 
 mod any;
+mod codec;
+mod protobuf;
 mod protocol;
 mod repr;
+mod serialized;
+
+/// Projects the `Space` parameter out of a `SmallBox<T, Space>` type.
+///
+/// [`ERASED_INLINE_CAP`]/[`ERASED_INLINE_ALIGN`] use this to read the inline
+/// capacity straight off [`dumping::ErasedMessage`]'s own `SmallBox`
+/// instantiation, rather than duplicating the size as a hand-copied
+/// constant that could silently drift from it.
+trait SmallBoxSpace {
+    type Space;
+}
+
+impl<T: ?Sized, Space> SmallBoxSpace for smallbox::SmallBox<T, Space> {
+    type Space = Space;
+}
+
+type ErasedSpace = <dumping::ErasedMessage as SmallBoxSpace>::Space;
+
+/// Inline capacity of [`dumping::ErasedMessage`]'s `SmallBox`, in bytes.
+///
+/// A message that fits within this bound *and* is no more aligned than
+/// [`ERASED_INLINE_ALIGN`] is erased in place; anything larger or
+/// over-aligned spills into a per-message heap allocation on the dumping hot
+/// path.
+#[doc(hidden)]
+pub const ERASED_INLINE_CAP: usize = size_of::<ErasedSpace>();
+
+/// Inline alignment of [`dumping::ErasedMessage`]'s `SmallBox`; see
+/// [`ERASED_INLINE_CAP`].
+#[doc(hidden)]
+pub const ERASED_INLINE_ALIGN: usize = align_of::<ErasedSpace>();
 
 // === Message ===
 
@@ -54,6 +89,56 @@ pub trait Message:
 
     // Private API.
 
+    /// `true` iff erasing this message via [`Message::_erase`] stays inline in
+    /// the `SmallBox` instead of spilling to the heap.
+    ///
+    /// `_erase` boxes a bare `Self` (`smallbox!(self.clone())`), so the bound is
+    /// on `size_of::<Self>()`, not on the `MessageRepr` wrapper; `smallbox` also
+    /// spills when the value is more aligned than the inline space, hence the
+    /// `align_of` term.
+    ///
+    /// Picked up by [`MessageVTable`]'s `inline` field and surfaced as an
+    /// aggregate count via [`MessageVTable::always_allocating_message_types`].
+    /// By itself this is only an informational flag and fails no build. The
+    /// compile-time check is [`Message::_assert_inline`] and the registry
+    /// count is [`MessageVTable::always_allocating_message_types`]; nothing in
+    /// this crate invokes either for a registered message type.
+    #[doc(hidden)]
+    const INLINE: bool =
+        size_of::<Self>() <= ERASED_INLINE_CAP && align_of::<Self>() <= ERASED_INLINE_ALIGN;
+
+    /// The margin, in bytes, between [`ERASED_INLINE_CAP`] and
+    /// `size_of::<Self>()`. Computing it as a subtraction (rather than just
+    /// re-exposing [`Message::INLINE`] as a bool) means a message that doesn't
+    /// fit overflows this constant at compile time, and the resulting
+    /// `E0080` names both operands — the offending size and the cap — in the
+    /// diagnostic.
+    #[doc(hidden)]
+    const _INLINE_SIZE_MARGIN: usize = ERASED_INLINE_CAP - size_of::<Self>();
+
+    /// Same as [`Message::_INLINE_SIZE_MARGIN`], for alignment.
+    #[doc(hidden)]
+    const _INLINE_ALIGN_MARGIN: usize = ERASED_INLINE_ALIGN - align_of::<Self>();
+
+    /// Forces [`Message::_INLINE_SIZE_MARGIN`] and
+    /// [`Message::_INLINE_ALIGN_MARGIN`] to be evaluated for `Self`, turning a
+    /// message that doesn't fit the inline erasure budget into a compile
+    /// error instead of a silent per-message heap allocation on every
+    /// [`Message::_erase`].
+    ///
+    /// The margins are evaluated when a call to this function is compiled for
+    /// a concrete message type. Being a trait method, it is not a `const fn`
+    /// and cannot initialize a `const` item; an item such as
+    /// `const _: usize = <MyMessage as Message>::_INLINE_SIZE_MARGIN;` makes
+    /// the same size check in a const context. Nothing in this crate calls it
+    /// for a registered message type.
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _assert_inline() {
+        let _ = Self::_INLINE_SIZE_MARGIN;
+        let _ = Self::_INLINE_ALIGN_MARGIN;
+    }
+
     #[doc(hidden)]
     fn _type_id() -> MessageTypeId;
 
@@ -80,24 +165,96 @@ pub trait Message:
         AnyMessage::from_real(self)
     }
 
+    /// Fails with [`DecodeError`] if `any` is still in its pending-bytes state
+    /// and those bytes don't decode as `Self` — e.g. a corrupt payload from
+    /// another node.
+    ///
     /// # Safety
     ///
     /// The caller must ensure that `any` holds this message type.
     #[doc(hidden)]
     #[inline(always)]
-    unsafe fn _from_any(any: AnyMessage) -> Self {
+    unsafe fn _from_any(any: AnyMessage) -> Result<Self, DecodeError> {
         any.into_real()
     }
 
+    /// Fails with [`DecodeError`] if `any` is still in its pending-bytes state
+    /// and those bytes don't decode as `Self` — e.g. a corrupt payload from
+    /// another node.
+    ///
     /// # Safety
     ///
     /// The caller must ensure that `any` holds this message type.
     #[doc(hidden)]
     #[inline(always)]
-    unsafe fn _from_any_ref(any: &AnyMessage) -> &Self {
+    unsafe fn _from_any_ref(any: &AnyMessage) -> Result<&Self, DecodeError> {
         any.as_real_ref()
     }
 
+    /// Whether [`Message::_erase`] keeps this message inline in the `SmallBox`.
+    ///
+    /// Mirrors [`Message::INLINE`] as a method, for callers that hold a
+    /// message value rather than naming its type.
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _erased_inline(&self) -> bool {
+        Self::INLINE
+    }
+
+    /// The on-wire representation this message type uses across nodes. Set by
+    /// the `#[message(codec = ...)]` attribute; defaults to serde-derived.
+    #[doc(hidden)]
+    const WIRE_FORMAT: WireFormat = WireFormat::Serde;
+
+    /// Encodes this message onto `buf` in [`Message::WIRE_FORMAT`]. Selected by
+    /// the `#[message(codec = ...)]` attribute: [`WireFormat::Serde`] appends
+    /// the serde-derived (MessagePack) bytes, [`WireFormat::Protobuf`] wraps
+    /// them in a protobuf length-delimited field.
+    #[doc(hidden)]
+    fn _encode(&self, buf: &mut Vec<u8>) {
+        match Self::WIRE_FORMAT {
+            WireFormat::Serde => {
+                rmp_serde::encode::write(buf, self).expect("failed to serialize message");
+            }
+            WireFormat::Protobuf => {
+                let payload = rmp_serde::to_vec(self).expect("failed to serialize message");
+                protobuf::put_payload(buf, &payload);
+            }
+        }
+    }
+
+    /// Decodes a message of this type from `bytes` in [`Message::WIRE_FORMAT`];
+    /// the counterpart of [`Message::_encode`].
+    ///
+    /// `bytes` can be attacker-controlled: for a message carried lazily by
+    /// [`AnyMessage`], this runs on the first downcast/`Debug`/routing access
+    /// to bytes a peer sent, well after the network codec's own framing and
+    /// length checks admitted the packet. A well-framed packet can still carry
+    /// a corrupt payload, so this returns [`DecodeError`] instead of
+    /// panicking — a bad body must not be remotely triggerable into killing
+    /// the receiving actor.
+    #[doc(hidden)]
+    fn _decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        match Self::WIRE_FORMAT {
+            WireFormat::Serde => rmp_serde::from_slice(bytes).map_err(DecodeError::new),
+            WireFormat::Protobuf => {
+                let payload = protobuf::get_payload(bytes)
+                    .ok_or_else(|| DecodeError::msg("malformed protobuf message"))?;
+                rmp_serde::from_slice(payload).map_err(DecodeError::new)
+            }
+        }
+    }
+
+    /// Serializes this message once into an owning [`SerializedMessage`] that
+    /// several consumers (the network sender, the dumper) can share as cheap
+    /// refcounted clones instead of re-encoding the same payload each time.
+    #[doc(hidden)]
+    fn _serialize_owned(&self) -> SerializedMessage {
+        let mut buf = Vec::new();
+        self._encode(&mut buf);
+        SerializedMessage::from_parts(self._vtable(), Arc::from(buf))
+    }
+
     #[doc(hidden)]
     #[inline(always)]
     fn _erase(&self) -> dumping::ErasedMessage {
@@ -132,6 +289,32 @@ pub trait Message:
         let repr = MessageRepr::new(self);
         ptr::write(ptr.cast::<MessageRepr<Self>>().as_ptr(), repr);
     }
+
+    /// Decodes `bytes` in place, completing the transition of a lazily-decoded
+    /// [`AnyMessage`] from its pending-bytes state to a fully decoded value.
+    /// Invoked through the vtable on the first access that needs the concrete
+    /// `T`, so that routing and re-forwarding — which re-serialize directly
+    /// from the retained bytes — never pay for a decode. `ptr` is left
+    /// untouched on [`DecodeError`]; the caller owns freeing it.
+    ///
+    /// # Safety
+    ///
+    /// Behavior is undefined if any of the following conditions are violated:
+    /// * `ptr` must be [valid] for writes.
+    /// * `ptr` must be properly aligned for `MessageRepr<Self>`.
+    /// * The value behind `ptr` must be uninitialized; it is written only on
+    ///   `Ok`, never dropped.
+    ///
+    /// [valid]: https://doc.rust-lang.org/stable/std/ptr/index.html#safety
+    #[doc(hidden)]
+    #[inline(always)]
+    unsafe fn _deserialize_into(
+        ptr: NonNull<MessageRepr>,
+        bytes: &[u8],
+    ) -> Result<(), DecodeError> {
+        Self::_decode(bytes)?._write(ptr);
+        Ok(())
+    }
 }
 
 // === Request ===
@@ -0,0 +1,450 @@
+use std::{alloc, cell::UnsafeCell, fmt, ptr::NonNull, sync::Arc};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{DecodeError, Message, MessageRepr, MessageTypeId, MessageVTable, SerializedMessage};
+
+// === AnyMessage ===
+
+/// A type-erased message.
+///
+/// It can be in one of two states:
+/// * **decoded** — owns a heap [`MessageRepr`] of the concrete type;
+/// * **pending** — holds the bytes received over the wire (owned by a
+///   [`SerializedMessage`]) together with the vtable resolved by protocol+name,
+///   and decodes lazily on the first access that needs the concrete type.
+///
+/// Routing and re-forwarding never touch the concrete value, so a message that
+/// only passes through a node is re-serialized straight from the retained
+/// bytes without ever being decoded.
+pub struct AnyMessage {
+    // `UnsafeCell`, not a lock: `Message` is bound `Send` only, not `Sync` (a
+    // message can carry non-`Sync` interior mutability, e.g. a `Cell` field),
+    // so `AnyMessage` must stay `Send` + `!Sync` — `UnsafeCell` enforces the
+    // `!Sync` half automatically, with no manual `Sync` impl anywhere to
+    // override it. Being `!Sync` also means a shared `&AnyMessage` can never
+    // reach a second thread, so the pending-to-decoded transition done
+    // through `&self` (`_from_any_ref`, `Debug`) can't race in the first
+    // place — a lock would only add overhead here, not soundness.
+    state: UnsafeCell<State>,
+}
+
+enum State {
+    Decoded(NonNull<MessageRepr>),
+    Pending(SerializedMessage),
+}
+
+// SAFETY: a `State` is owned exclusively by the single `AnyMessage` that
+// holds it (the pointer is never aliased), so moving it to another thread is
+// sound; nothing here makes it `Sync`, which is what keeps `AnyMessage` from
+// becoming `Sync` too.
+unsafe impl Send for AnyMessage {}
+
+impl AnyMessage {
+    /// Wraps a concrete message, eagerly decoded.
+    #[inline(always)]
+    pub fn new<M: Message>(message: M) -> Self {
+        Self::from_real(message)
+    }
+
+    #[inline(always)]
+    pub(crate) fn from_real<M: Message>(message: M) -> Self {
+        let ptr = NonNull::from(Box::leak(Box::new(MessageRepr::new(message)))).cast();
+        Self {
+            state: UnsafeCell::new(State::Decoded(ptr)),
+        }
+    }
+
+    /// Wraps an already-serialized message, carrying its owning buffer along the
+    /// receive path so that re-forwarding never has to re-encode. The concrete
+    /// value is materialized lazily on the first access that needs it.
+    #[inline(always)]
+    pub fn from_serialized(serialized: SerializedMessage) -> Self {
+        Self::from_pending(serialized)
+    }
+
+    #[inline(always)]
+    fn from_pending(serialized: SerializedMessage) -> Self {
+        Self {
+            state: UnsafeCell::new(State::Pending(serialized)),
+        }
+    }
+
+    /// Ensures the message is decoded and returns the stable pointer to its
+    /// `MessageRepr`. Decoding happens at most once — the pending bytes are
+    /// turned into a heap `MessageRepr` via the vtable's `decode` hook and the
+    /// state flips to decoded.
+    ///
+    /// Fails with [`DecodeError`] if the pending bytes — which may have come
+    /// straight from another node — don't decode as the vtable's type; the
+    /// state is left `Pending` so a caller that swallows the error doesn't
+    /// wedge the message into a broken `Decoded` state.
+    fn materialize(&self) -> Result<NonNull<MessageRepr>, DecodeError> {
+        // SAFETY: `AnyMessage` is `!Sync` (see the `state` field comment), so
+        // a shared `&self` can never be reached from two threads at once;
+        // the borrow below is confined to this function and the decoded
+        // allocation it yields is never moved afterwards.
+        unsafe {
+            let state = &mut *self.state.get();
+            match state {
+                State::Decoded(ptr) => Ok(*ptr),
+                State::Pending(serialized) => {
+                    let vtable = serialized.vtable();
+                    let layout = vtable.repr_layout;
+                    let ptr = match NonNull::new(alloc::alloc(layout)) {
+                        Some(ptr) => ptr.cast::<MessageRepr>(),
+                        None => alloc::handle_alloc_error(layout),
+                    };
+                    match (vtable.codec.decode)(ptr, serialized.bytes()) {
+                        Ok(()) => {
+                            *state = State::Decoded(ptr);
+                            Ok(ptr)
+                        }
+                        Err(err) => {
+                            alloc::dealloc(ptr.as_ptr().cast(), layout);
+                            Err(err)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The caller must ensure the carried message is of type `M`.
+    ///
+    /// The returned `&M` outlives `materialize`'s internal borrow of `state`,
+    /// which is sound only because `AnyMessage` is `!Sync`: `M` need not be
+    /// `Sync` (only `Message: Send` is required), so this reference must
+    /// never be reachable from a second thread while it's alive.
+    pub(crate) unsafe fn as_real_ref<M: Message>(&self) -> Result<&M, DecodeError> {
+        let ptr = self.materialize()?;
+        Ok(&ptr.cast::<MessageRepr<M>>().as_ref().data)
+    }
+
+    /// # Safety
+    ///
+    /// The caller must ensure the carried message is of type `M`.
+    pub(crate) unsafe fn into_real<M: Message>(self) -> Result<M, DecodeError> {
+        let ptr = self.materialize()?;
+        let data = M::_read(ptr);
+        // The data has been moved out; free the backing allocation without
+        // running the in-place drop, and skip our own `Drop`.
+        let layout = ptr.as_ref().vtable.repr_layout;
+        alloc::dealloc(ptr.as_ptr().cast(), layout);
+        std::mem::forget(self);
+        Ok(data)
+    }
+
+    /// Re-serializes the message for forwarding. A pending message reuses its
+    /// retained bytes verbatim; a decoded one is encoded once through the
+    /// vtable codec. Either way the result shares a single owning buffer.
+    pub fn to_serialized(&self) -> SerializedMessage {
+        // SAFETY: read-only access to the current state; see `materialize`.
+        unsafe {
+            match &*self.state.get() {
+                State::Pending(serialized) => serialized.clone(),
+                State::Decoded(ptr) => {
+                    let vtable = ptr.as_ref().vtable;
+                    // Encode exactly once: pre-sizing via `encoded_len` would
+                    // run a throwaway `_encode` first, defeating the whole
+                    // single-encode point of the owned buffer.
+                    let mut buf = Vec::new();
+                    (vtable.codec.encode)(*ptr, &mut buf);
+                    SerializedMessage::from_parts(vtable, Arc::from(buf))
+                }
+            }
+        }
+    }
+
+    /// Whether the message is still in its undecoded pending-bytes state.
+    #[cfg(test)]
+    pub(crate) fn is_pending(&self) -> bool {
+        // SAFETY: read-only access to the current state; see `materialize`.
+        unsafe { matches!(&*self.state.get(), State::Pending(_)) }
+    }
+
+    /// Whether the carried message is of type `M`.
+    pub fn is<M: Message>(&self) -> bool {
+        self._vtable().type_id == MessageTypeId::of::<M>()
+    }
+
+    /// Returns a reference to the carried message if it is of type `M`,
+    /// decoding it on first access.
+    ///
+    /// Returns `Ok(None)` on a type mismatch, same as before laziness. Returns
+    /// `Err` only when the type matches but the (possibly attacker-supplied)
+    /// pending bytes fail to decode as `M`.
+    pub fn downcast_ref<M: Message>(&self) -> Result<Option<&M>, DecodeError> {
+        if !self.is::<M>() {
+            return Ok(None);
+        }
+        // SAFETY: the type check above guarantees the stored message is an `M`.
+        unsafe { self.as_real_ref::<M>() }.map(Some)
+    }
+}
+
+impl Message for AnyMessage {
+    #[inline(always)]
+    fn _type_id() -> MessageTypeId {
+        MessageTypeId::of::<AnyMessage>()
+    }
+
+    #[inline(always)]
+    fn _vtable(&self) -> &'static MessageVTable {
+        // SAFETY: we only read the current state, which always holds a vtable.
+        unsafe {
+            match &*self.state.get() {
+                State::Decoded(ptr) => ptr.as_ref().vtable,
+                State::Pending(serialized) => serialized.vtable(),
+            }
+        }
+    }
+
+    // `AnyMessage` erases every concrete type, so it is a supertype of all.
+    #[inline(always)]
+    fn _is_supertype_of(_type_id: MessageTypeId) -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn _into_any(self) -> AnyMessage {
+        self
+    }
+
+    #[inline(always)]
+    unsafe fn _from_any(any: AnyMessage) -> Result<Self, DecodeError> {
+        Ok(any)
+    }
+
+    #[inline(always)]
+    unsafe fn _from_any_ref(any: &AnyMessage) -> Result<&Self, DecodeError> {
+        Ok(any)
+    }
+
+    // Forwarding reuses the retained bytes instead of re-encoding.
+    #[inline(always)]
+    fn _serialize_owned(&self) -> SerializedMessage {
+        self.to_serialized()
+    }
+}
+
+impl Clone for AnyMessage {
+    fn clone(&self) -> Self {
+        // SAFETY: read-only access to the current state; see `materialize`.
+        unsafe {
+            match &*self.state.get() {
+                // Cheap: bump the refcount of the shared buffer, no decode.
+                State::Pending(serialized) => Self::from_pending(serialized.clone()),
+                State::Decoded(ptr) => (ptr.as_ref().vtable.clone)(*ptr),
+            }
+        }
+    }
+}
+
+impl Drop for AnyMessage {
+    fn drop(&mut self) {
+        // SAFETY: exclusive access in `Drop`; a decoded message owns its repr.
+        unsafe {
+            if let State::Decoded(ptr) = *self.state.get() {
+                let vtable = ptr.as_ref().vtable;
+                (vtable.drop)(ptr);
+                alloc::dealloc(ptr.as_ptr().cast(), vtable.repr_layout);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for AnyMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Debug requires the concrete value, so it forces a decode. A corrupt
+        // payload isn't a bug in the formatting code, so fall back to a
+        // placeholder instead of propagating the error through `fmt::Result`.
+        match self.materialize() {
+            // SAFETY: `ptr` points to a live repr whose vtable matches it.
+            Ok(ptr) => unsafe { (ptr.as_ref().vtable.debug)(ptr, f) },
+            Err(err) => {
+                let vtable = self._vtable();
+                write!(
+                    f,
+                    "<{}::{} (failed to decode: {err})>",
+                    vtable.protocol, vtable.name
+                )
+            }
+        }
+    }
+}
+
+impl Serialize for AnyMessage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let serialized = self.to_serialized();
+        (
+            serialized.protocol(),
+            serialized.name(),
+            serialized.bytes().as_ref(),
+        )
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AnyMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (protocol, name, bytes) = <(String, String, Vec<u8>)>::deserialize(deserializer)?;
+        let vtable = MessageVTable::lookup(&protocol, &name)
+            .ok_or_else(|| de::Error::custom(format!("unknown message {protocol}::{name}")))?;
+        Ok(Self::from_pending(SerializedMessage::from_parts(
+            vtable,
+            Arc::from(bytes),
+        )))
+    }
+}
+
+// === erased vtable functions ===
+//
+// Picked up by the `#[message]` macro to fill the type-erased slots of
+// `MessageVTable`.
+
+pub(crate) unsafe fn clone_erased<M: Message>(ptr: NonNull<MessageRepr>) -> AnyMessage {
+    AnyMessage::from_real(ptr.cast::<MessageRepr<M>>().as_ref().data.clone())
+}
+
+pub(crate) unsafe fn debug_erased<M: Message>(
+    ptr: NonNull<MessageRepr>,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    fmt::Debug::fmt(&ptr.cast::<MessageRepr<M>>().as_ref().data, f)
+}
+
+pub(crate) unsafe fn drop_erased<M: Message>(ptr: NonNull<MessageRepr>) {
+    std::ptr::drop_in_place(ptr.cast::<MessageRepr<M>>().as_ptr());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{alloc::Layout, sync::OnceLock};
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::message::{MessageCodec, MessageRepr, MessageTypeId, MessageVTable};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Sample {
+        x: u32,
+    }
+
+    fn sample_vtable() -> &'static MessageVTable {
+        static VTABLE: OnceLock<MessageVTable> = OnceLock::new();
+        VTABLE.get_or_init(|| MessageVTable {
+            protocol: "test",
+            name: "Sample",
+            labels: Vec::new(),
+            dumping_allowed: false,
+            inline: true,
+            repr_layout: Layout::new::<MessageRepr<Sample>>(),
+            type_id: MessageTypeId::of::<Sample>(),
+            codec: MessageCodec::of::<Sample>(),
+            clone: clone_erased::<Sample>,
+            debug: debug_erased::<Sample>,
+            drop: drop_erased::<Sample>,
+        })
+    }
+
+    impl Message for Sample {
+        fn _type_id() -> MessageTypeId {
+            MessageTypeId::of::<Sample>()
+        }
+
+        fn _vtable(&self) -> &'static MessageVTable {
+            sample_vtable()
+        }
+    }
+
+    fn pending(sample: &Sample) -> (AnyMessage, Arc<[u8]>) {
+        let mut bytes = Vec::new();
+        sample._encode(&mut bytes);
+        let bytes: Arc<[u8]> = Arc::from(bytes);
+        let serialized = SerializedMessage::from_parts(sample_vtable(), bytes.clone());
+        (AnyMessage::from_serialized(serialized), bytes)
+    }
+
+    #[test]
+    fn inline_assert_passes_for_small_message() {
+        // `Sample` fits the inline budget, so this must not fail to compile
+        // (it would be a hard `E0080` error at the `_INLINE_SIZE_MARGIN`
+        // subtraction otherwise, not a runtime panic).
+        Sample::_assert_inline();
+    }
+
+    #[test]
+    fn is_does_not_decode() {
+        let (any, _) = pending(&Sample { x: 7 });
+        assert!(any.is_pending());
+        assert!(any.is::<Sample>());
+        // Type-checking reads the vtable only; the bytes stay undecoded.
+        assert!(any.is_pending());
+    }
+
+    #[test]
+    fn downcast_decodes_exactly_once() {
+        let (any, _) = pending(&Sample { x: 42 });
+        let first = any.downcast_ref::<Sample>().unwrap().unwrap();
+        assert_eq!(first.x, 42);
+        assert!(!any.is_pending());
+        let second = any.downcast_ref::<Sample>().unwrap().unwrap();
+        // A second access returns the same materialized value, not a re-decode.
+        assert!(std::ptr::eq(first, second));
+    }
+
+    #[test]
+    fn downcast_ref_reports_decode_error_instead_of_panicking() {
+        let serialized =
+            SerializedMessage::from_parts(sample_vtable(), Arc::from(b"\xff"[..].to_vec()));
+        let any = AnyMessage::from_serialized(serialized);
+        // The type matches (the vtable was resolved by protocol+name), but the
+        // body is garbage — a peer sending this must get a decode error back,
+        // not crash the receiving actor.
+        any.downcast_ref::<Sample>().unwrap_err();
+        // The failed decode didn't corrupt the state; it's still pending.
+        assert!(any.is_pending());
+    }
+
+    #[test]
+    fn to_serialized_reuses_pending_bytes() {
+        let (any, bytes) = pending(&Sample { x: 1 });
+        let again = any.to_serialized();
+        assert!(Arc::ptr_eq(&bytes, again.bytes()));
+        assert!(any.is_pending());
+    }
+
+    #[test]
+    fn to_serialized_encodes_decoded_message() {
+        let any = AnyMessage::from_real(Sample { x: 99 });
+        let serialized = any.to_serialized();
+        assert_eq!(serialized.protocol(), "test");
+        assert_eq!(serialized.name(), "Sample");
+        assert_eq!(
+            Sample::_decode(serialized.bytes()).unwrap(),
+            Sample { x: 99 }
+        );
+    }
+
+    #[test]
+    fn into_real_roundtrips() {
+        let (any, _) = pending(&Sample { x: 5 });
+        let sample = unsafe { any.into_real::<Sample>() }.unwrap();
+        assert_eq!(sample, Sample { x: 5 });
+    }
+
+    #[test]
+    fn serialize_emits_protocol_name_and_bytes() {
+        let any = AnyMessage::from_real(Sample { x: 3 });
+        let encoded = rmp_serde::to_vec(&any).unwrap();
+        let (protocol, name, bytes): (String, String, Vec<u8>) =
+            rmp_serde::from_slice(&encoded).unwrap();
+        assert_eq!(protocol, "test");
+        assert_eq!(name, "Sample");
+        assert_eq!(Sample::_decode(&bytes).unwrap(), Sample { x: 3 });
+    }
+}
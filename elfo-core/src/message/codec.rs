@@ -0,0 +1,122 @@
+use std::{fmt, ptr::NonNull};
+
+use super::{Message, MessageRepr};
+
+// === DecodeError ===
+
+/// A message's wire bytes failed to decode.
+///
+/// Surfaced rather than panicking because the bytes can come straight from
+/// another node: [`Message::_decode`] (and the lazily-decoded
+/// [`AnyMessage`](super::AnyMessage) path built on it) treat a well-framed
+/// but corrupt payload as a recoverable decode error, not a process-killing
+/// bug.
+#[derive(Debug)]
+pub struct DecodeError(Box<dyn std::error::Error + Send + Sync>);
+
+impl DecodeError {
+    pub(crate) fn new(err: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self(Box::new(err))
+    }
+
+    pub(crate) fn msg(text: impl Into<String>) -> Self {
+        #[derive(Debug)]
+        struct Msg(String);
+        impl fmt::Display for Msg {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+        impl std::error::Error for Msg {}
+        Self::new(Msg(text.into()))
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.0.as_ref())
+    }
+}
+
+// === WireFormat ===
+
+/// The on-wire representation a message uses for cross-node transfer.
+///
+/// Selected per message type by the `#[message]` macro (e.g.
+/// `#[message(codec = protobuf)]`) and defaulting to [`WireFormat::Serde`].
+/// The network layer dispatches through the [`MessageCodec`] stored in the
+/// [`MessageVTable`] rather than calling serde directly, so a protocol can pin
+/// the size and stability of its wire layout or interop with external
+/// protobuf services.
+///
+/// [`MessageVTable`]: super::MessageVTable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WireFormat {
+    /// The default serde-derived encoding (MessagePack).
+    Serde,
+    /// A protobuf varint / length-delimited wire encoding.
+    Protobuf,
+}
+
+/// Encodes the message behind `ptr` into `buf`.
+///
+/// # Safety
+///
+/// `ptr` must point to a live, properly initialized `MessageRepr<T>` of the
+/// type this codec belongs to.
+pub type EncodeFn = unsafe fn(ptr: NonNull<MessageRepr>, buf: &mut Vec<u8>);
+
+/// Decodes `bytes` into the uninitialized `MessageRepr` at `ptr`.
+///
+/// `bytes` may be attacker-controlled (received from another node), so a
+/// malformed payload is returned as [`DecodeError`], not panicked on; `ptr`
+/// is left uninitialized in that case.
+///
+/// # Safety
+///
+/// `ptr` must be valid and aligned for writes of `MessageRepr<T>` and must be
+/// uninitialized; it is written only on `Ok`, never dropped.
+pub type DecodeFn = unsafe fn(ptr: NonNull<MessageRepr>, bytes: &[u8]) -> Result<(), DecodeError>;
+
+// === MessageCodec ===
+
+/// The set of wire-codec function pointers embedded in a [`MessageVTable`].
+///
+/// [`MessageVTable`]: super::MessageVTable
+#[derive(Clone, Copy)]
+pub struct MessageCodec {
+    pub wire_format: WireFormat,
+    pub encode: EncodeFn,
+    pub decode: DecodeFn,
+}
+
+impl MessageCodec {
+    /// Builds the codec for `M` from its [`Message`] codec methods. The vtable
+    /// stores the result, so the network layer can encode/decode an erased repr
+    /// through the pointers without monomorphizing at every call site.
+    pub const fn of<M: Message>() -> Self {
+        Self {
+            wire_format: M::WIRE_FORMAT,
+            encode: encode_erased::<M>,
+            decode: decode_erased::<M>,
+        }
+    }
+}
+
+unsafe fn encode_erased<M: Message>(ptr: NonNull<MessageRepr>, buf: &mut Vec<u8>) {
+    ptr.cast::<MessageRepr<M>>().as_ref().data._encode(buf);
+}
+
+unsafe fn decode_erased<M: Message>(
+    ptr: NonNull<MessageRepr>,
+    bytes: &[u8],
+) -> Result<(), DecodeError> {
+    M::_deserialize_into(ptr, bytes)
+}
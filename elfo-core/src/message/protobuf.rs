@@ -0,0 +1,118 @@
+//! Minimal protobuf wire-format primitives used by the [`WireFormat::Protobuf`]
+//! codec path: base-128 varints and length-delimited fields, as described in
+//! the protobuf encoding reference. A message selecting this format has its
+//! payload carried as a single length-delimited field (field number 1), which
+//! is enough to interop with external services that expect a protobuf envelope.
+//!
+//! [`WireFormat::Protobuf`]: super::WireFormat::Protobuf
+
+/// Wire type 2 (length-delimited), field number 1: `(1 << 3) | 2`.
+const PAYLOAD_TAG: u64 = 0b1010;
+
+/// Appends `value` to `buf` as a base-128 varint.
+pub(crate) fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Reads a base-128 varint from `bytes` at `offset`, advancing it past the
+/// consumed bytes. Returns `None` on a truncated or overlong varint.
+pub(crate) fn get_varint(bytes: &[u8], offset: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*offset)?;
+        *offset += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Frames `payload` as the length-delimited field 1 of a protobuf message.
+pub(crate) fn put_payload(buf: &mut Vec<u8>, payload: &[u8]) {
+    put_varint(buf, PAYLOAD_TAG);
+    put_varint(buf, payload.len() as u64);
+    buf.extend_from_slice(payload);
+}
+
+/// Extracts the field-1 payload bytes written by [`put_payload`]. Returns
+/// `None` on any malformed framing — wrong tag, truncated length, or a length
+/// that overflows or runs past the end of `bytes` — so attacker-controlled
+/// input is rejected rather than panicking.
+pub(crate) fn get_payload(bytes: &[u8]) -> Option<&[u8]> {
+    let mut offset = 0;
+    if get_varint(bytes, &mut offset)? != PAYLOAD_TAG {
+        return None;
+    }
+    let len = usize::try_from(get_varint(bytes, &mut offset)?).ok()?;
+    let end = offset.checked_add(len)?;
+    bytes.get(offset..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varint_roundtrip() {
+        for value in [0u64, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut buf = Vec::new();
+            put_varint(&mut buf, value);
+            let mut offset = 0;
+            assert_eq!(get_varint(&buf, &mut offset), Some(value));
+            assert_eq!(offset, buf.len());
+        }
+    }
+
+    #[test]
+    fn varint_rejects_truncated() {
+        // A lone continuation byte never terminates.
+        let mut offset = 0;
+        assert_eq!(get_varint(&[0x80], &mut offset), None);
+    }
+
+    #[test]
+    fn varint_rejects_overlong() {
+        // Ten continuation bytes overflow 64 bits without terminating.
+        let mut offset = 0;
+        assert_eq!(get_varint(&[0x80; 10], &mut offset), None);
+    }
+
+    #[test]
+    fn payload_roundtrip() {
+        let mut buf = Vec::new();
+        put_payload(&mut buf, b"hello");
+        assert_eq!(get_payload(&buf), Some(&b"hello"[..]));
+    }
+
+    #[test]
+    fn payload_rejects_wrong_tag() {
+        let mut buf = Vec::new();
+        put_varint(&mut buf, 0);
+        put_varint(&mut buf, 0);
+        assert_eq!(get_payload(&buf), None);
+    }
+
+    #[test]
+    fn payload_rejects_overflowing_length() {
+        let mut buf = Vec::new();
+        put_varint(&mut buf, PAYLOAD_TAG);
+        put_varint(&mut buf, u64::MAX);
+        buf.extend_from_slice(b"short");
+        assert_eq!(get_payload(&buf), None);
+    }
+
+    #[test]
+    fn payload_rejects_truncated_body() {
+        let mut buf = Vec::new();
+        put_varint(&mut buf, PAYLOAD_TAG);
+        put_varint(&mut buf, 10);
+        buf.extend_from_slice(b"abc");
+        assert_eq!(get_payload(&buf), None);
+    }
+}
@@ -0,0 +1,127 @@
+use std::{alloc::Layout, fmt, ptr::NonNull};
+
+use metrics::Label;
+
+use super::{AnyMessage, MessageCodec, MessageRepr, MessageTypeId};
+
+// === MessageVTable ===
+
+/// A per-message-type table of type-erased operations, shared by every value of
+/// that type. Produced by the `#[message]` macro and collected into
+/// [`MESSAGE_VTABLES_LIST`] at link time so it can be resolved by protocol+name
+/// on the receive path.
+pub struct MessageVTable {
+    pub protocol: &'static str,
+    pub name: &'static str,
+    pub labels: Vec<Label>,
+    pub dumping_allowed: bool,
+    /// Mirrors [`Message::INLINE`]: whether this message type stays inline in
+    /// the `SmallBox` on [`Message::_erase`] instead of spilling to the heap.
+    ///
+    /// [`Message::INLINE`]: super::Message::INLINE
+    pub inline: bool,
+    pub repr_layout: Layout,
+    pub type_id: MessageTypeId,
+    /// Wire codec selected by `#[message(codec = ...)]`; the network layer
+    /// dispatches encoding and decoding through it rather than calling serde
+    /// directly.
+    pub codec: MessageCodec,
+    /// Clones the message behind the erased repr into a fresh `AnyMessage`.
+    pub clone: unsafe fn(NonNull<MessageRepr>) -> AnyMessage,
+    /// Formats the message behind the erased repr.
+    pub debug: unsafe fn(NonNull<MessageRepr>, &mut fmt::Formatter<'_>) -> fmt::Result,
+    /// Drops the message behind the erased repr in place.
+    pub drop: unsafe fn(NonNull<MessageRepr>),
+}
+
+#[linkme::distributed_slice]
+pub static MESSAGE_VTABLES_LIST: [&'static MessageVTable] = [..];
+
+impl MessageVTable {
+    /// Resolves the vtable of a message received over the wire by its protocol
+    /// and name, so the payload can be decoded (or re-forwarded) without the
+    /// concrete type being known at the call site.
+    pub(crate) fn lookup(protocol: &str, name: &str) -> Option<&'static MessageVTable> {
+        MESSAGE_VTABLES_LIST
+            .iter()
+            .copied()
+            .find(|vtable| vtable.protocol == protocol && vtable.name == name)
+    }
+
+    /// Registered message types that always spill to the heap on
+    /// [`Message::_erase`] (`inline == false`), i.e. messages that either
+    /// don't fit [`ERASED_INLINE_CAP`]/[`ERASED_INLINE_ALIGN`] or were
+    /// explicitly opted out via `#[message(inline = false)]`.
+    ///
+    /// Sets the `elfo_always_allocating_message_types` gauge to the count, so
+    /// an unexpected jump shows up in metrics; also returns the vtables
+    /// themselves so a caller can log a one-time startup warning naming them.
+    /// [`MESSAGE_VTABLES_LIST`] is complete from program start, so one call
+    /// covers every registered type. Nothing in this crate calls it; the
+    /// gauge is set only when a caller does.
+    ///
+    /// [`Message::_erase`]: super::Message::_erase
+    /// [`ERASED_INLINE_CAP`]: super::ERASED_INLINE_CAP
+    /// [`ERASED_INLINE_ALIGN`]: super::ERASED_INLINE_ALIGN
+    pub fn always_allocating_message_types() -> Vec<&'static MessageVTable> {
+        let offenders = always_allocating(MESSAGE_VTABLES_LIST.iter().copied());
+        metrics::gauge!("elfo_always_allocating_message_types").set(offenders.len() as f64);
+        offenders
+    }
+}
+
+fn always_allocating<'a>(
+    vtables: impl IntoIterator<Item = &'a MessageVTable>,
+) -> Vec<&'a MessageVTable> {
+    vtables
+        .into_iter()
+        .filter(|vtable| !vtable.inline)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+
+    use super::*;
+    use crate::message::{clone_erased, debug_erased, drop_erased, Message};
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    struct Sample;
+
+    impl Message for Sample {
+        fn _type_id() -> MessageTypeId {
+            MessageTypeId::of::<Sample>()
+        }
+
+        fn _vtable(&self) -> &'static MessageVTable {
+            static VTABLE: OnceLock<MessageVTable> = OnceLock::new();
+            VTABLE.get_or_init(|| vtable(true))
+        }
+    }
+
+    fn vtable(inline: bool) -> MessageVTable {
+        MessageVTable {
+            protocol: "test",
+            name: if inline { "Inline" } else { "Heap" },
+            labels: Vec::new(),
+            dumping_allowed: false,
+            inline,
+            repr_layout: Layout::new::<MessageRepr<Sample>>(),
+            type_id: MessageTypeId::of::<Sample>(),
+            codec: MessageCodec::of::<Sample>(),
+            clone: clone_erased::<Sample>,
+            debug: debug_erased::<Sample>,
+            drop: drop_erased::<Sample>,
+        }
+    }
+
+    #[test]
+    fn always_allocating_filters_out_inline_vtables() {
+        let inline = vtable(true);
+        let heap = vtable(false);
+        let offenders = always_allocating([&inline, &heap]);
+        assert_eq!(offenders.len(), 1);
+        assert_eq!(offenders[0].name, "Heap");
+    }
+}
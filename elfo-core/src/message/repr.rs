@@ -0,0 +1,38 @@
+use std::any::TypeId;
+
+use super::{Message, MessageVTable};
+
+// === MessageTypeId ===
+
+/// A stable, cheaply comparable identity of a message type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MessageTypeId(TypeId);
+
+impl MessageTypeId {
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn of<M: Message>() -> Self {
+        Self(TypeId::of::<M>())
+    }
+}
+
+// === MessageRepr ===
+
+/// The in-memory layout shared by every message: a vtable pointer followed by
+/// the concrete message data. `MessageRepr<()>` is the type-erased view handed
+/// around behind a `NonNull`, `MessageRepr<M>` the concrete one.
+#[repr(C)]
+pub struct MessageRepr<M: ?Sized = ()> {
+    pub(crate) vtable: &'static MessageVTable,
+    pub(crate) data: M,
+}
+
+impl<M: Message> MessageRepr<M> {
+    #[inline(always)]
+    pub(crate) fn new(message: M) -> Self {
+        Self {
+            vtable: message._vtable(),
+            data: message,
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::{fmt, sync::Arc};
+
+use super::MessageVTable;
+
+// === SerializedMessage ===
+
+/// A message serialized once into owned wire bytes, shared without re-encoding.
+///
+/// Forwarding one message to several sinks (the network codec and the dumper,
+/// say) would otherwise encode the same payload independently for each. A
+/// `SerializedMessage` owns the encoded bytes in a refcounted allocation
+/// alongside the originating [`MessageVTable`], so every consumer holds a cheap
+/// thin reference to the same buffer and pays for the encoding only once.
+///
+/// Produced via [`Message::_serialize_owned`] and consumed via
+/// [`AnyMessage::from_serialized`].
+///
+/// [`Message::_serialize_owned`]: super::Message::_serialize_owned
+/// [`AnyMessage::from_serialized`]: super::AnyMessage::from_serialized
+#[derive(Clone)]
+pub struct SerializedMessage {
+    vtable: &'static MessageVTable,
+    bytes: Arc<[u8]>,
+}
+
+impl SerializedMessage {
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn from_parts(vtable: &'static MessageVTable, bytes: Arc<[u8]>) -> Self {
+        Self { vtable, bytes }
+    }
+
+    /// The vtable of the message that produced these bytes.
+    #[inline(always)]
+    pub fn vtable(&self) -> &'static MessageVTable {
+        self.vtable
+    }
+
+    /// The shared wire bytes.
+    #[inline(always)]
+    pub fn bytes(&self) -> &Arc<[u8]> {
+        &self.bytes
+    }
+
+    #[inline(always)]
+    pub fn protocol(&self) -> &'static str {
+        self.vtable.protocol
+    }
+
+    #[inline(always)]
+    pub fn name(&self) -> &'static str {
+        self.vtable.name
+    }
+}
+
+impl fmt::Debug for SerializedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SerializedMessage")
+            .field("protocol", &self.protocol())
+            .field("name", &self.name())
+            .field("len", &self.bytes.len())
+            .finish()
+    }
+}